@@ -1,6 +1,7 @@
 use std::process::{self, Command, ExitStatus};
 use std::path::PathBuf;
 
+#[derive(Clone)]
 pub struct GitContext {
 	working_dir: Option<PathBuf>,
 }
@@ -12,7 +13,11 @@ impl GitContext {
 		}
 	}
 
-	pub fn run_raw<S>(&self, args: impl IntoIterator<Item=S>) -> anyhow::Result<GitOutput>
+	/// Run git synchronously, blocking the current thread. Used by the sync API
+	/// below (`query`/`try_query`/...), which the subcommands (e.g. the install
+	/// path) use for one-off queries; it blocks even when called from inside the
+	/// async runtime.
+	pub fn run_raw_sync<S>(&self, args: impl IntoIterator<Item=S>) -> anyhow::Result<GitOutput>
 		where S: AsRef<std::ffi::OsStr>
 	{
 		let args: Vec<_> = args.into_iter().collect();
@@ -41,10 +46,42 @@ impl GitContext {
 		})
 	}
 
+	/// Run git without blocking the async runtime's worker thread, so a slow
+	/// query (e.g. `git log -p` on a big repo) doesn't stall the rest of the UI.
+	pub async fn run_raw<S>(&self, args: impl IntoIterator<Item=S>) -> anyhow::Result<GitOutput>
+		where S: AsRef<std::ffi::OsStr>
+	{
+		let args: Vec<_> = args.into_iter().collect();
+		let arg_strings: Vec<_> = args.iter().map(AsRef::as_ref).collect();
+
+		log::info!("> git {arg_strings:?}");
+
+		let mut command = tokio::process::Command::new("git");
+		command.args(args);
+		command.kill_on_drop(true);
+
+		if let Some(dir) = self.working_dir.as_ref() {
+			command.current_dir(dir);
+		}
+
+		let process::Output{ status, stdout, stderr } = command.output().await?;
+
+		log::info!(" -> status: {status:?}");
+
+		let stdout = std::str::from_utf8(&stdout)?.trim().to_owned();
+		let stderr = std::str::from_utf8(&stderr)?.trim().to_owned();
+
+		Ok(GitOutput {
+			status,
+			stdout,
+			stderr,
+		})
+	}
+
 	pub fn query<S>(&self, args: impl IntoIterator<Item=S>) -> anyhow::Result<String>
 		where S: AsRef<std::ffi::OsStr>
 	{
-		let GitOutput{status, stdout, stderr} = self.run_raw(args)?;
+		let GitOutput{status, stdout, stderr} = self.run_raw_sync(args)?;
 
 		if !status.success() {
 			log::error!("{stderr}");
@@ -57,7 +94,7 @@ impl GitContext {
 	pub fn try_query<S>(&self, args: impl IntoIterator<Item=S>) -> anyhow::Result<Option<String>>
 		where S: AsRef<std::ffi::OsStr>
 	{
-		let GitOutput{status, stdout, stderr} = self.run_raw(args)?;
+		let GitOutput{status, stdout, stderr} = self.run_raw_sync(args)?;
 		match status.code() {
 			Some(0) => Ok(Some(stdout)),
 			Some(1) => Ok(None),
@@ -65,6 +102,21 @@ impl GitContext {
 		}
 	}
 
+	/// Async counterpart to [`query`](Self::query), for use from the picker's
+	/// background preview tasks.
+	pub async fn query_async<S>(&self, args: impl IntoIterator<Item=S>) -> anyhow::Result<String>
+		where S: AsRef<std::ffi::OsStr>
+	{
+		let GitOutput{status, stdout, stderr} = self.run_raw(args).await?;
+
+		if !status.success() {
+			log::error!("{stderr}");
+			anyhow::bail!("{stderr}");
+		}
+
+		Ok(stdout)
+	}
+
 	pub fn query_list<S>(&self, args: impl IntoIterator<Item=S>) -> anyhow::Result<Vec<String>>
 		where S: AsRef<std::ffi::OsStr>
 	{
@@ -75,6 +127,18 @@ impl GitContext {
 			.collect()
 	}
 
+	/// Async counterpart to [`query_list`](Self::query_list), for background
+	/// batch loading (e.g. [`FilterableList::with_loader`](crate::ui::FilterableList::with_loader)).
+	pub async fn query_list_async<S>(&self, args: impl IntoIterator<Item=S>) -> anyhow::Result<Vec<String>>
+		where S: AsRef<std::ffi::OsStr>
+	{
+		self.query_async(args).await?
+			.lines()
+			.map(String::from)
+			.map(Ok)
+			.collect()
+	}
+
 	pub fn query_success<S>(&self, args: impl IntoIterator<Item=S>) -> anyhow::Result<bool>
 		where S: AsRef<std::ffi::OsStr>
 	{
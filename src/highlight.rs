@@ -0,0 +1,74 @@
+use std::sync::OnceLock;
+
+use crossterm::style::Color;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color as SyntectColor, ThemeSet};
+use syntect::parsing::SyntaxSet;
+
+/// A line of preview text, pre-split into colored runs ready for
+/// [`ViewportDrawContext::print_styled`](crate::ui::ViewportDrawContext::print_styled).
+pub type HighlightedLine = Vec<(Color, String)>;
+
+fn syntax_set() -> &'static SyntaxSet {
+	static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+	SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme_set() -> &'static ThemeSet {
+	static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+	THEME_SET.get_or_init(ThemeSet::load_defaults)
+}
+
+/// Highlight a preview's text for display. Unified diffs (detected by `+`/`-`/`@@`
+/// line prefixes) get their added/removed/hunk-header lines colored directly;
+/// anything else is run through syntect as plain text.
+pub fn highlight_text(text: &str) -> Vec<HighlightedLine> {
+	if is_diff(text) {
+		return highlight_diff(text);
+	}
+
+	highlight_blob(text)
+}
+
+/// Whether `text` looks like a unified diff, judged by its first few lines.
+fn is_diff(text: &str) -> bool {
+	text.lines()
+		.take(20)
+		.any(|line| line.starts_with('+') || line.starts_with('-') || line.starts_with("@@"))
+}
+
+fn highlight_diff(text: &str) -> Vec<HighlightedLine> {
+	text.lines()
+		.map(|line| {
+			let color = match line.as_bytes().first() {
+				Some(b'+') => Color::Green,
+				Some(b'-') => Color::Red,
+				_ if line.starts_with("@@") => Color::Cyan,
+				_ => Color::Grey,
+			};
+
+			vec![(color, line.to_owned())]
+		})
+		.collect()
+}
+
+fn highlight_blob(text: &str) -> Vec<HighlightedLine> {
+	let syntax_set = syntax_set();
+	let syntax = syntax_set.find_syntax_plain_text();
+
+	let theme = &theme_set().themes["base16-ocean.dark"];
+	let mut highlighter = HighlightLines::new(syntax, theme);
+
+	text.lines()
+		.map(|line| {
+			let ranges = highlighter.highlight_line(line, syntax_set).unwrap_or_default();
+			ranges.into_iter()
+				.map(|(style, span)| (to_crossterm_color(style.foreground), span.to_owned()))
+				.collect()
+		})
+		.collect()
+}
+
+fn to_crossterm_color(color: SyntectColor) -> Color {
+	Color::Rgb { r: color.r, g: color.g, b: color.b }
+}
@@ -1,16 +1,21 @@
-use std::process::{Command, ExitCode};
+use std::process::ExitCode;
+use std::collections::HashSet;
 use anyhow::Context;
 use clap::Parser;
-use fuzzy_matcher::FuzzyMatcher;
-use fuzzy_matcher::skim::SkimMatcherV2;
 
 use std::io::{stdout};
 use crossterm::{
 	*,
 	tty::*,
 	event::*,
+	style::Color,
 };
 
+mod ui;
+mod highlight;
+mod git;
+use ui::FilterableList;
+
 #[derive(Parser, Debug)]
 #[command(version, author, about)]
 struct MainArgs {
@@ -18,6 +23,10 @@ struct MainArgs {
 	#[arg(long, global=true)]
 	log: bool,
 
+	/// Run as if git-utils was started in this directory, instead of the current one.
+	#[arg(long, global=true)]
+	working_dir: Option<std::path::PathBuf>,
+
 	#[command(subcommand)]
 	subcommand: ArgCommand,
 }
@@ -47,10 +56,25 @@ enum ArgCommand {
 		remote: bool,
 	},
 
-	// SearchCommits
-	// DeleteBranches
+	/// Interactively delete one or more local branches
+	DeleteBranches {
+		/// Force delete branches, even if they haven't been merged.
+		#[arg(long, short)]
+		force: bool,
+	},
+
+	/// Interactively search commit history
+	SearchCommits {
+		/// Search history across all refs instead of just the current branch
+		#[arg(long, short)]
+		all: bool,
 
-	// /// 
+		/// Check out the selected commit instead of showing it
+		#[arg(long, short)]
+		checkout: bool,
+	},
+
+	// ///
 	// CreateBranch {
 	// 	/// Create branch from a commit instead of a branch
 	// 	#[arg(long, short)]
@@ -60,7 +84,15 @@ enum ArgCommand {
 
 
 fn main() -> ExitCode {
-	match run() {
+	let runtime = match tokio::runtime::Builder::new_multi_thread().enable_all().build() {
+		Ok(runtime) => runtime,
+		Err(err) => {
+			eprintln!("{err}");
+			return ExitCode::FAILURE;
+		}
+	};
+
+	match runtime.block_on(run()) {
 		Err(err) => {
 			eprintln!("{err}");
 			ExitCode::FAILURE
@@ -71,7 +103,7 @@ fn main() -> ExitCode {
 }
 
 
-fn run() -> anyhow::Result<()> {
+async fn run() -> anyhow::Result<()> {
 	let args = MainArgs::parse();
 
 	if !stdout().is_tty() {
@@ -84,6 +116,8 @@ fn run() -> anyhow::Result<()> {
 		simplelog::WriteLogger::init(log::LevelFilter::Info, simplelog::Config::default(), log_file)?;
 	}
 
+	let git_ctx = git::GitContext::new(&args);
+
 	execute!{
 		stdout(),
 		event::PushKeyboardEnhancementFlags(event::KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES),
@@ -118,7 +152,7 @@ fn run() -> anyhow::Result<()> {
 			for (alias, command) in aliases {
 				let config_name = format!("alias.{alias}");
 				let config_command = format!("!{} {command}", current_path.display());
-				git(args.iter().cloned().chain([config_name.as_str(), config_command.as_str()]))?;
+				git_ctx.run(args.iter().cloned().chain([config_name.as_str(), config_command.as_str()]))?;
 
 				println!("Aliasing `git {alias}` to `git-utils {command}`");
 			}
@@ -130,21 +164,109 @@ fn run() -> anyhow::Result<()> {
 				true => "refs/remotes"
 			};
 
-			// TODO(pat.m): include upstream in list
-			let mut branch_list = git_list(["for-each-ref", "--format", "%(refname:lstrip=2)", refspec])?;
-			branch_list.retain(|branch| !branch.ends_with("/HEAD"));
-			if branch_list.is_empty() {
+			struct BranchEntry {
+				name: String,
+				upstream: Option<String>,
+				ahead: u32,
+				behind: u32,
+				is_current: bool,
+			}
+
+			// Fields can contain spaces, so join them with NUL rather than whitespace.
+			let format = "%(refname:lstrip=2)%00%(upstream:lstrip=2)%00%(upstream:track)";
+			let lines = git_ctx.query_list(["for-each-ref", "--format", format, refspec])?;
+			let current_branch = git_ctx.query(["symbolic-ref", "--short", "HEAD"]).ok();
+
+			let mut entries: Vec<BranchEntry> = lines.iter()
+				.filter_map(|line| {
+					let mut fields = line.splitn(3, '\0');
+					let name = fields.next()?.to_owned();
+					if name.ends_with("/HEAD") {
+						return None;
+					}
+
+					let upstream = fields.next().filter(|s| !s.is_empty()).map(String::from);
+					let (ahead, behind) = parse_ahead_behind(fields.next().unwrap_or(""));
+					let is_current = current_branch.as_deref() == Some(name.as_str());
+
+					Some(BranchEntry { name, upstream, ahead, behind, is_current })
+				})
+				.collect();
+
+			if entries.is_empty() {
 				anyhow::bail!("No branches to switch to.");
 			}
 
-			let index = list_prompt(&branch_list)?;
-			let selected_branch = branch_list[index].as_str();
+			// Float the current branch to the top.
+			entries.sort_by_key(|entry| !entry.is_current);
+
+			let mut list = FilterableList::new("Switch to branch: ");
+			for entry in entries {
+				let name = entry.name.clone();
+				list.insert(name, entry);
+			}
+
+			let list = list.with_row_renderer(|entry: &BranchEntry, ctx: &mut ui::ViewportDrawContext, row, col, width| {
+				let mut column = col;
+				let mut remaining = (width as usize).saturating_sub(col as usize);
+
+				let marker = if entry.is_current { "* " } else { "  " };
+				ctx.set_fg_color(Color::Green);
+				ctx.print_at(marker, row, column);
+				ctx.reset_color();
+				column += 2;
+				remaining = remaining.saturating_sub(marker.chars().count());
+
+				let name = ui::truncate_to_width(&entry.name, remaining);
+				ctx.print_at(&name, row, column);
+				column += name.chars().count() as u16 + 1;
+				remaining = remaining.saturating_sub(name.chars().count() + 1);
+
+				if entry.ahead > 0 && remaining > 0 {
+					ctx.set_fg_color(Color::Green);
+					let segment = ui::truncate_to_width(&format!("\u{2191}{} ", entry.ahead), remaining);
+					ctx.print_at(&segment, row, column);
+					column += segment.chars().count() as u16;
+					remaining = remaining.saturating_sub(segment.chars().count());
+					ctx.reset_color();
+				}
+
+				if entry.behind > 0 && remaining > 0 {
+					ctx.set_fg_color(Color::Red);
+					let segment = ui::truncate_to_width(&format!("\u{2193}{} ", entry.behind), remaining);
+					ctx.print_at(&segment, row, column);
+					column += segment.chars().count() as u16;
+					remaining = remaining.saturating_sub(segment.chars().count());
+					ctx.reset_color();
+				}
+
+				if let Some(upstream) = entry.upstream.as_ref() {
+					if remaining > 0 {
+						ctx.set_fg_color(Color::DarkGrey);
+						let segment = ui::truncate_to_width(&format!("\u{2192} {upstream}"), remaining);
+						ctx.print_at(&segment, row, column);
+						ctx.reset_color();
+					}
+				}
+			});
+
+			let list = list.with_preview({
+				let git_ctx = git_ctx.clone();
+				move |entry: &BranchEntry| {
+					let git_ctx = git_ctx.clone();
+					let branch = entry.name.clone();
+					Box::pin(async move { preview_branch_log(&git_ctx, &branch).await })
+				}
+			});
+
+			let selected_entry = list.run().await?;
+			let selected_branch = selected_entry.name.as_str();
 
 			if remote {
 				let (_remote, local_branch) = selected_branch.split_once('/').context("git for-each-ref yielded info in unexpected format")?;
 
-				if ref_exists(&format!("refs/heads/{local_branch}"))? {
-					match get_upstream(local_branch)? {
+				if git_ctx.query_success(["show-ref", "--quiet", &format!("refs/heads/{local_branch}")])? {
+					match git_ctx.try_query(["rev-parse", "--quiet", "--abbrev-ref", "--verify", &format!("{local_branch}@{{upstream}}")])? {
 						Some(current_upstream) => {
 							if current_upstream != selected_branch {
 								anyhow::bail!("Branch with name '{local_branch}' already exists but has different tracking branch '{current_upstream}' (expected '{selected_branch}')")
@@ -156,277 +278,159 @@ fn run() -> anyhow::Result<()> {
 						}
 					}
 
-					git(["switch", local_branch])?;
+					git_ctx.run(["switch", local_branch])?;
 					println!("Switched to branch {local_branch}, tracking {selected_branch}");
 				} else {
-					git(["switch", "--track", selected_branch, "--create", local_branch])?;
+					git_ctx.run(["switch", "--track", selected_branch, "--create", local_branch])?;
 					println!("Switched to new branch {local_branch}, tracking {selected_branch}");
 				}
 
 			} else {
-				git(["switch", selected_branch])?;
+				git_ctx.run(["switch", selected_branch])?;
 				println!("Switched to branch {selected_branch}");
 			}
 		}
-	}
-
-	Ok(())
-}
 
-fn list_prompt<I: std::fmt::Display>(items: &[I]) -> anyhow::Result<usize> {
-	anyhow::ensure!(!items.is_empty());
-
-	let mut out = stdout();
-
-	let mut selected_index = 0usize;
-	let mut cursor_index = 0usize;
-	let mut offset = 0;
-	let mut filter_string = String::new();
+		ArgCommand::DeleteBranches { force } => {
+			let mut branch_list = git_ctx.query_list(["for-each-ref", "--format", "%(refname:lstrip=2)", "refs/heads"])?;
+			branch_list.retain(|branch| !branch.ends_with("/HEAD"));
 
-	let (_, height) = terminal::size()?;
-	let desired_height = height.min(items.len() as u16 + 1);
-	let max_visible_items = desired_height as usize - 1;
+			let current_branch = git_ctx.query(["symbolic-ref", "--short", "HEAD"]).ok();
+			branch_list.retain(|branch| Some(branch) != current_branch.as_ref());
 
-	// Clear enough space
-	{
-		let num_newlines = desired_height.saturating_sub(1);
-		for _ in 0..num_newlines { print!("\n"); }
-		execute!{out, cursor::MoveUp(num_newlines)}?;
-	}
-
-	let start_row = cursor::position()?.1;
+			if branch_list.is_empty() {
+				anyhow::bail!("No branches to delete.");
+			}
 
-	let _guard = on_drop(|| {
-		execute!{
-			stdout(),
-			cursor::MoveTo(0, start_row),
-			terminal::Clear(terminal::ClearType::FromCursorDown),
-			style::ResetColor,
-		}.unwrap();
-	});
+			let merged_branches: HashSet<_> = git_ctx.query_list(["branch", "--format", "%(refname:lstrip=2)", "--merged"])?.into_iter().collect();
 
-	let matcher = SkimMatcherV2::default();
-	let item_strings: Vec<_> = items.iter().map(|item| item.to_string()).collect();
+			let mut list = FilterableList::new("Delete branches: ");
+			for branch in branch_list {
+				let label = match merged_branches.contains(&branch) {
+					true => format!("{branch}  (merged)"),
+					false => branch.clone(),
+				};
 
-	#[derive(Ord, PartialOrd, Eq, PartialEq)]
-	struct FilteredItem<'s> {
-		score: i64,
-		original_index: usize,
-		text: &'s str,
-	}
+				list.insert(label, branch);
+			}
 
-	let mut filtered_items: Vec<_> = item_strings.iter().enumerate()
-		.map(|(index, item)| FilteredItem {
-			score: 0,
-			original_index: index,
-			text: item,
-		})
-		.collect();
+			let selected_branches = list.run_multi().await?;
+			anyhow::ensure!(!selected_branches.is_empty(), "No branches selected.");
 
-	loop {
-		execute!{
-			out,
-			cursor::MoveTo(0, start_row),
-			terminal::Clear(terminal::ClearType::FromCursorDown),
-			style::Print("Switch to branch: "),
-		}?;
-
-		let cursor_start = cursor::position()?.0;
-
-		print!("{filter_string}");
-
-		// Render list.
-		for (index, &FilteredItem{ text, .. }) in filtered_items.iter().enumerate().skip(offset).take(max_visible_items) {
-			let is_selected = index == selected_index;
-			let marker = match is_selected {
-				true => '>',
-				false => ' ',
+			let delete_flag = match force {
+				true => "-D",
+				false => "-d",
 			};
 
-			if is_selected {
-				queue!{
-					out, 
-					style::SetForegroundColor(style::Color::Black),
-					style::SetBackgroundColor(style::Color::White),
-				}?;
+			let mut failures = 0;
+
+			for branch in selected_branches {
+				let git::GitOutput{status, stderr, ..} = git_ctx.run_raw_sync(["branch", delete_flag, &branch])?;
+				if status.success() {
+					println!("Deleted branch {branch}");
+				} else {
+					eprintln!("Failed to delete branch {branch}: {stderr}");
+					failures += 1;
+				}
 			}
 
-			print!("\n{marker} {text}{}", style::ResetColor);
+			if failures > 0 {
+				anyhow::bail!("Failed to delete {failures} branch(es). Re-run with --force to delete unmerged branches.");
+			}
 		}
 
-		execute!{
-			out,
-			cursor::MoveTo(cursor_start + cursor_index as u16, start_row),
-		}?;
-
-		let _guard = start_raw_mode()?;
-
-		match event::read()? {
-			Event::Key(KeyEvent{ code, modifiers, kind: KeyEventKind::Press, .. }) => match (code, modifiers) {
-				(KeyCode::Enter, _) if !filtered_items.is_empty() => break,
+		ArgCommand::SearchCommits { all, checkout } => {
+			struct CommitEntry {
+				sha: String,
+			}
 
-				(KeyCode::Char('c'), KeyModifiers::CONTROL) | (KeyCode::Esc, _) => {
-					anyhow::bail!("Cancelled")
+			// Fetched in batches as the user scrolls, since the full history of a
+			// repo can be huge - see `FilterableList::with_loader`. Async so the
+			// picker stays responsive (redrawing, Ctrl+C) while git runs.
+			async fn fetch_commits(git_ctx: &git::GitContext, all: bool, skip: usize, count: usize) -> anyhow::Result<Vec<(String, CommitEntry)>> {
+				let format = "%H%00%s%00%an%00%ar";
+				let skip = skip.to_string();
+				let count = count.to_string();
+
+				let mut args = vec!["log", "--format", format, "--skip", &skip, "--max-count", &count];
+				if all {
+					args.push("--all");
 				}
 
-				// Note: ctrl+backspace produces ^h on my machine.
-				(KeyCode::Backspace, KeyModifiers::CONTROL) | (KeyCode::Char('h'), KeyModifiers::CONTROL) => {
-					// Not quite right but whatever
-					filter_string.clear();
-					cursor_index = 0;
-				}
+				git_ctx.query_list_async(args).await?.iter()
+					.filter_map(|line| {
+						let mut fields = line.splitn(4, '\0');
+						let sha = fields.next()?.to_owned();
+						let subject = fields.next()?;
+						let author = fields.next()?;
+						let relative_date = fields.next()?;
+
+						let abbrev = &sha[..sha.len().min(7)];
+						let label = format!("{abbrev}  {subject}  ({author}, {relative_date})");
+
+						Some((label, CommitEntry { sha }))
+					})
+					.map(Ok)
+					.collect()
+			}
 
-				(KeyCode::Backspace, _) => if let Some(index) = cursor_index.checked_sub(1) {
-					filter_string.remove(index);
-					cursor_index -= 1;
-				}
+			let mut list = FilterableList::new("Search commits: ");
+			for (label, entry) in fetch_commits(&git_ctx, all, 0, LOG_BATCH_SIZE).await? {
+				list.insert(label, entry);
+			}
 
-				(KeyCode::Delete, _) => if !filter_string.is_empty() {
-					filter_string.remove(cursor_index);
+			let list = list.with_loader({
+				let git_ctx = git_ctx.clone();
+				move |skip, count| {
+					let git_ctx = git_ctx.clone();
+					Box::pin(async move { fetch_commits(&git_ctx, all, skip, count).await })
 				}
+			});
 
-				(KeyCode::Home, _) => { cursor_index = 0; }
-				(KeyCode::End, _) => { cursor_index = filter_string.len(); }
-
-				(KeyCode::Left, _) => { cursor_index = cursor_index.saturating_sub(1); }
-				(KeyCode::Right, _) => { cursor_index += 1; }
-
-				(KeyCode::Up, _) => { selected_index = selected_index.saturating_sub(1); }
-				(KeyCode::Down, _) => { selected_index += 1; }
-				(KeyCode::PageUp, _) => { selected_index = selected_index.saturating_sub(5); }
-				(KeyCode::PageDown, _) => { selected_index += 5; }
+			let selected = list.run().await?;
 
-				(KeyCode::Char(ch), _) => if ch.is_ascii() {
-					filter_string.insert(cursor_index, ch);
-					cursor_index += 1;
-				}
-
-				_ => {}
+			if checkout {
+				git_ctx.run(["checkout", &selected.sha])?;
+				println!("Checked out commit {}", selected.sha);
+			} else {
+				let stdout = git_ctx.query(["show", &selected.sha])?;
+				println!("{stdout}");
 			}
-
-			_ => {}
-		}
-
-		// Refilter
-		filtered_items.clear();
-		filtered_items.extend(
-			item_strings.iter().enumerate()
-				.filter_map(|(index, item)| {
-					matcher.fuzzy_match(item, &filter_string)
-						.map(|score| FilteredItem {
-							score: -score,
-							original_index: index,
-							text: item.as_str(),
-						})
-				})
-		);
-
-		filtered_items.sort();
-
-		// Keep indices in bounds
-		cursor_index = cursor_index.min(filter_string.len());
-
-		if !filtered_items.is_empty() {
-			selected_index = selected_index.min(filtered_items.len() - 1);
-		}
-
-		// Make sure selection is in view
-		if selected_index >= offset + max_visible_items {
-			offset = selected_index - max_visible_items + 1;
-		} else if selected_index < offset {
-			offset = selected_index;
 		}
 	}
 
-	anyhow::ensure!(selected_index < filtered_items.len());
-
-	Ok(filtered_items[selected_index].original_index)
-}
-
-struct GitOutput {
-	code: i32,
-	stdout: String,
-	stderr: String,
-}
-
-fn git<S>(args: impl IntoIterator<Item=S>) -> anyhow::Result<GitOutput>
-	where S: AsRef<std::ffi::OsStr>
-{
-	let args: Vec<_> = args.into_iter().collect();
-	let arg_strings: Vec<_> = args.iter().map(AsRef::as_ref).collect();
-
-	log::info!("> git {arg_strings:?}");
-
-	let output = Command::new("git")
-		.args(args)
-		.output()?;
-
-	let stdout = std::str::from_utf8(&output.stdout)?.trim().to_owned();
-	let stderr = std::str::from_utf8(&output.stderr)?.trim().to_owned();
-
-	Ok(GitOutput {
-		code: output.status.code().unwrap_or(i32::MAX),
-		stdout,
-		stderr,
-	})
+	Ok(())
 }
 
-fn git_stdout<S>(args: impl IntoIterator<Item=S>) -> anyhow::Result<String>
-	where S: AsRef<std::ffi::OsStr>
-{
-	let GitOutput{code, stdout, stderr} = git(args)?;
+/// Initial batch size for `search-commits`'s lazily-loaded commit list.
+const LOG_BATCH_SIZE: usize = 200;
 
-	log::info!(" -> status: {code}");
-
-	if code != 0 {
-		log::error!("{stderr}");
-		anyhow::bail!("{stderr}");
+/// Recent log/diff for the branch under the cursor, for `Switch`'s preview pane.
+/// Uses `GitContext::query_async` so a slow `git log -p` doesn't stall the rest
+/// of the picker.
+async fn preview_branch_log(git_ctx: &git::GitContext, branch: &str) -> String {
+	match git_ctx.query_async(["log", "-p", "--color=never", "-n", "5", branch]).await {
+		Ok(stdout) => stdout,
+		Err(err) => format!("Failed to load preview: {err}"),
 	}
-
-	Ok(stdout)
 }
 
-fn ref_exists(refname: &str) -> anyhow::Result<bool> {
-	let GitOutput{code, stderr, ..} = git(["show-ref", "--quiet", refname])?;
+/// Parse a `%(upstream:track)` value like `[ahead 2, behind 1]` into its counts.
+fn parse_ahead_behind(track: &str) -> (u32, u32) {
+	let track = track.trim_matches(|c| c == '[' || c == ']');
 
-	match code {
-		0 => return Ok(true),
-		1 => return Ok(false),
-		_ => {}
-	}
+	let mut ahead = 0;
+	let mut behind = 0;
 
-	anyhow::bail!("{stderr}");
-}
-
-fn get_upstream(branch: &str) -> anyhow::Result<Option<String>> {
-	let GitOutput{code, stdout, stderr} = git(["rev-parse", "--quiet", "--abbrev-ref", "--verify", &format!("{branch}@{{upstream}}")])?;
-
-	match code {
-		0 => return Ok(Some(stdout)),
-		1 => return Ok(None),
-		_ => {}
+	for part in track.split(", ") {
+		if let Some(n) = part.strip_prefix("ahead ") {
+			ahead = n.parse().unwrap_or(0);
+		} else if let Some(n) = part.strip_prefix("behind ") {
+			behind = n.parse().unwrap_or(0);
+		}
 	}
 
-	anyhow::bail!("{stderr}");
-}
-
-fn git_list<S>(args: impl IntoIterator<Item=S>) -> anyhow::Result<Vec<String>>
-	where S: AsRef<std::ffi::OsStr>
-{
-	git_stdout(args)?
-		.lines()
-		.map(String::from)
-		.map(Ok)
-		.collect()
-}
-
-
-
-fn start_raw_mode() -> anyhow::Result<impl Drop> {
-	terminal::enable_raw_mode()?;
-	Ok(on_drop(|| {
-		terminal::disable_raw_mode().unwrap()
-	}))
+	(ahead, behind)
 }
 
 
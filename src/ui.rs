@@ -1,5 +1,8 @@
 use std::io::{stdout, Stdout, Write};
 use std::fmt::Display;
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
 
 use crate::on_drop;
 
@@ -9,6 +12,9 @@ use crossterm::{
 	style::Color,
 };
 
+use futures::StreamExt;
+use tokio::task::JoinHandle;
+
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
 
@@ -24,9 +30,24 @@ pub struct FilterableList<T> {
 	prompt_text: String,
 
 	filtered_items: Vec<usize>,
-	needs_refilter: bool,
+
+	preview: Option<Box<dyn Fn(&T) -> PreviewFuture>>,
+	row_renderer: Option<Box<dyn Fn(&T, &mut ViewportDrawContext, u16, u16, u16)>>,
+
+	loader: Option<Box<dyn Fn(usize, usize) -> LoaderFuture<T>>>,
+	loader_exhausted: bool,
 }
 
+/// A preview fetch in progress. Boxed and pinned so `with_preview` can accept
+/// any `async` block or future without the caller needing to name its type.
+pub type PreviewFuture = Pin<Box<dyn Future<Output = String> + Send>>;
+
+/// A batch fetch in progress, as kicked off by [`FilterableList::with_loader`].
+/// Boxed and pinned for the same reason as [`PreviewFuture`]; `Send` so it can
+/// be driven on a `tokio::spawn`ed task rather than blocking the picker's event
+/// loop while it resolves.
+pub type LoaderFuture<T> = Pin<Box<dyn Future<Output = anyhow::Result<Vec<(String, T)>>> + Send>>;
+
 impl<T> FilterableList<T> {
 	pub fn new(prompt_text: impl Into<String>) -> Self {
 		FilterableList {
@@ -34,13 +55,17 @@ impl<T> FilterableList<T> {
 			prompt_text: prompt_text.into(),
 
 			filtered_items: Vec::new(),
-			needs_refilter: false,
+
+			preview: None,
+			row_renderer: None,
+
+			loader: None,
+			loader_exhausted: false,
 		}
 	}
 
 	pub fn insert(&mut self, display: impl Into<String>, value: T) {
 		self.items.push(ListItem { display: display.into(), value });
-		self.needs_refilter = true;
 	}
 
 	pub fn insert_formatted(&mut self, value: T)
@@ -48,92 +73,295 @@ impl<T> FilterableList<T> {
 	{
 		self.insert(value.to_string(), value);
 	}
+
+	/// Show a preview pane alongside the list, populated by `f` for the currently
+	/// highlighted item. `f` is expected to return quickly (it just kicks off a
+	/// background task, e.g. `Box::pin(async move { ... })`); the task is polled
+	/// in the background and cancelled if the selection moves on before it resolves.
+	pub fn with_preview(mut self, f: impl Fn(&T) -> PreviewFuture + 'static) -> Self {
+		self.preview = Some(Box::new(f));
+		self
+	}
+
+	/// Customize how each row is drawn, for cases where a plain display string
+	/// isn't expressive enough (e.g. colored status decorations). Called with the
+	/// item's value, the draw context, the row/column to start drawing at, and
+	/// the list column's total width (for clipping, same as the no-renderer
+	/// fallback does via `truncate_to_width`) - the fuzzy matcher still only
+	/// ever sees `display`.
+	pub fn with_row_renderer(mut self, f: impl Fn(&T, &mut ViewportDrawContext, u16, u16, u16) + 'static) -> Self {
+		self.row_renderer = Some(Box::new(f));
+		self
+	}
+
+	/// Lazily populate items in batches rather than loading everything up front,
+	/// for sources where fetching everything eagerly would be wasteful (e.g.
+	/// full commit history). `f(skip, count)` is called for more `(display, value)`
+	/// pairs once the view nears the end of what's loaded so far; once it returns
+	/// fewer than `count` items the list is considered exhausted and isn't called
+	/// again. Items passed to [`insert`](Self::insert) before [`run`](Self::run)
+	/// is called seed the initial screenful.
+	///
+	/// `f` is expected to return quickly, the same as `with_preview`'s callback -
+	/// it just kicks off a background task (e.g. `Box::pin(async move { ... })`),
+	/// which is then driven on a `tokio::spawn`ed task rather than blocking the
+	/// event loop while the batch comes in.
+	pub fn with_loader(mut self, f: impl Fn(usize, usize) -> LoaderFuture<T> + 'static) -> Self {
+		self.loader = Some(Box::new(f));
+		self
+	}
 }
 
+/// Minimum total viewport width below which the preview pane is dropped and the
+/// list is drawn full-width, as in the no-preview case.
+const MIN_WIDTH_FOR_PREVIEW: u16 = 30;
+
+/// Batch size for lists populated via [`FilterableList::with_loader`].
+const LOAD_BATCH: usize = 200;
+
+/// A filtered-in item, ranked for display. `score` sorts ascending (best match
+/// first) since it's stored negated; `text` is only borrowed for the fuzzy
+/// match and the no-`row_renderer` fallback, never for anything that outlives
+/// a single frame.
+#[derive(Ord, PartialOrd, Eq, PartialEq)]
+struct FilteredItem<'s> {
+	score: i64,
+	original_index: usize,
+	text: &'s str,
+}
 
+/// Refilter `items` against `filter_string`, then clamp the caret/selection/
+/// scroll offset so they stay in range. Shared between [`FilterableList::run`]
+/// and [`FilterableList::run_multi`] so the navigation rules only live in one
+/// place.
+fn refilter_and_clamp<'s, T>(
+	items: &'s [ListItem<T>],
+	filter_string: &str,
+	matcher: &SkimMatcherV2,
+	caret_index: &mut usize,
+	selected_index: &mut usize,
+	offset: &mut usize,
+	max_visible_items: usize,
+) -> Vec<FilteredItem<'s>> {
+	let mut filtered_items: Vec<_> = items.iter().enumerate()
+		.filter_map(|(index, item)| {
+			matcher.fuzzy_match(&item.display, filter_string)
+				.map(|score| FilteredItem {
+					score: -score,
+					original_index: index,
+					text: item.display.as_str(),
+				})
+		})
+		.collect();
 
+	filtered_items.sort();
 
+	*caret_index = (*caret_index).min(filter_string.len());
 
-impl<T> FilterableList<T> {
-	pub fn run(mut self) -> anyhow::Result<T> {
+	if !filtered_items.is_empty() {
+		*selected_index = (*selected_index).min(filtered_items.len() - 1);
+	}
+
+	// Make sure max number of items possible are visible.
+	*offset = (*offset).min(filtered_items.len().saturating_sub(max_visible_items));
+
+	// Make sure selection is in view.
+	if *selected_index >= *offset + max_visible_items {
+		*offset = *selected_index - max_visible_items + 1;
+	} else if *selected_index < *offset {
+		*offset = *selected_index;
+	}
+
+	filtered_items
+}
+
+/// Outcome of a keypress handled by [`handle_navigation_key`], the filter/caret/
+/// selection keys common to both [`FilterableList::run`] and `run_multi`.
+enum NavOutcome {
+	/// The key was acted on; redraw and keep going.
+	Handled,
+	/// Not one of the shared keys - the caller should try its own bindings.
+	Unhandled,
+	/// Enter, with at least one item filtered in.
+	Confirm,
+	/// Ctrl+C or Esc.
+	Cancel,
+}
+
+/// Handle the filter-editing and list-navigation keys shared by both picker
+/// flavors (everything except the multi-select checkmark keys).
+fn handle_navigation_key(
+	code: KeyCode,
+	modifiers: KeyModifiers,
+	filtered_len: usize,
+	max_visible_items: usize,
+	filter_string: &mut String,
+	caret_index: &mut usize,
+	selected_index: &mut usize,
+) -> NavOutcome {
+	match (code, modifiers) {
+		(KeyCode::Enter, _) if filtered_len > 0 => return NavOutcome::Confirm,
+
+		(KeyCode::Char('c'), KeyModifiers::CONTROL) | (KeyCode::Esc, _) => return NavOutcome::Cancel,
+
+		// Note: ctrl+backspace produces ^h on my machine.
+		(KeyCode::Backspace, KeyModifiers::CONTROL) | (KeyCode::Char('h'), KeyModifiers::CONTROL) => {
+			// Not quite right but whatever
+			filter_string.clear();
+			*caret_index = 0;
+		}
+
+		(KeyCode::Backspace, _) => if let Some(index) = caret_index.checked_sub(1) {
+			filter_string.remove(index);
+			*caret_index -= 1;
+		}
+
+		(KeyCode::Delete, _) => if !filter_string.is_empty() {
+			filter_string.remove(*caret_index);
+		}
+
+		(KeyCode::Home, _) => { *caret_index = 0; }
+		(KeyCode::End, _) => { *caret_index = filter_string.len(); }
+
+		(KeyCode::Left, _) => { *caret_index = caret_index.saturating_sub(1); }
+		(KeyCode::Right, _) => { *caret_index += 1; }
+
+		(KeyCode::Up, _) => { *selected_index = selected_index.saturating_sub(1); }
+		(KeyCode::Down, _) => { *selected_index += 1; }
+
+		(KeyCode::PageUp, KeyModifiers::CONTROL) => { *selected_index = 0; }
+		(KeyCode::PageDown, KeyModifiers::CONTROL) => { *selected_index = filtered_len; }
+
+		(KeyCode::PageUp, _) => { *selected_index = selected_index.saturating_sub(max_visible_items); }
+		(KeyCode::PageDown, _) => { *selected_index += max_visible_items; }
+
+		(KeyCode::Char(ch), _) if ch.is_ascii() => {
+			filter_string.insert(*caret_index, ch);
+			*caret_index += 1;
+		}
+
+		_ => return NavOutcome::Unhandled,
+	}
+
+	NavOutcome::Handled
+}
+
+
+
+
+impl<T: Send + 'static> FilterableList<T> {
+	/// Kick off fetching another batch from the loader once the view nears the
+	/// end of what's filtered in, same as `with_preview`'s background fetch.
+	/// Shared by `run`/`run_multi` so the threshold only needs tuning in one
+	/// place; a no-op when `with_loader` was never called or a fetch is
+	/// already in flight.
+	fn maybe_load_more(
+		&self,
+		filtered_len: usize,
+		offset: usize,
+		max_visible_items: usize,
+		load_handle: &mut Option<JoinHandle<anyhow::Result<Vec<(String, T)>>>>,
+	) {
+		if self.loader_exhausted || load_handle.is_some()
+			|| offset + max_visible_items + LOAD_BATCH / 2 < filtered_len {
+			return;
+		}
+
+		if let Some(loader) = self.loader.as_ref() {
+			let skip = self.items.len();
+			*load_handle = Some(tokio::spawn(loader(skip, LOAD_BATCH)));
+		}
+	}
+
+	/// Apply a batch fetched by [`maybe_load_more`](Self::maybe_load_more),
+	/// updating `loader_exhausted` so a short final batch stops further fetches.
+	fn apply_loaded_batch(&mut self, batch: Vec<(String, T)>) {
+		self.loader_exhausted = batch.len() < LOAD_BATCH;
+
+		for (display, value) in batch {
+			self.items.push(ListItem { display, value });
+		}
+	}
+
+	pub async fn run(mut self) -> anyhow::Result<T> {
 		anyhow::ensure!(!self.items.is_empty());
 
-		let mut viewport = InlineViewport::start(self.items.len() + 1)?;
+		// Previews want more room than a bare list, which only ever needs one row per item.
+		let desired_height = match self.preview.is_some() {
+			true => (self.items.len() + 1).max(15),
+			false => self.items.len() + 1,
+		};
+
+		let mut viewport = InlineViewport::start(desired_height)?;
 
 		let mut selected_index = 0usize;
 		let mut caret_index = 0usize;
 		let mut offset = 0;
 		let mut filter_string = String::new();
 
+		// The preview task for the currently highlighted item, if one is still in
+		// flight, and the text it last resolved to. Recomputed only when the
+		// selection moves to a different item, and cancelled if it's superseded
+		// before it resolves.
+		let mut preview_index: Option<usize> = None;
+		let mut preview_handle: Option<JoinHandle<String>> = None;
+		let mut preview_lines: Option<Vec<crate::highlight::HighlightedLine>> = None;
+
+		// The in-flight batch fetch kicked off by `maybe_load_more`, if any.
+		let mut load_handle: Option<JoinHandle<anyhow::Result<Vec<(String, T)>>>> = None;
+
 		let matcher = SkimMatcherV2::default();
 
-		#[derive(Ord, PartialOrd, Eq, PartialEq)]
-		struct FilteredItem<'s> {
-			score: i64,
-			original_index: usize,
-			text: &'s str,
-		}
+		let mut filtered_items: Vec<FilteredItem> = Vec::new();
 
-		let mut filtered_items: Vec<_> = self.items.iter().enumerate()
-			.map(|(index, item)| FilteredItem {
-				score: 0,
-				original_index: index,
-				text: item.display.as_str(),
-			})
-			.collect();
+		let _raw_mode_guard = start_raw_mode()?;
+		let mut events = EventStream::new();
 
 		'main: loop {
 			let max_visible_items = viewport.usable_height() as usize - 1;
 
-			self.needs_refilter = true;
-
-			// Refilter
-			if self.needs_refilter {
-				self.needs_refilter = false;
-				filtered_items.clear();
-				filtered_items.extend(
-					self.items.iter().enumerate()
-						.filter_map(|(index, item)| {
-							matcher.fuzzy_match(&item.display, &filter_string)
-								.map(|score| FilteredItem {
-									score: -score,
-									original_index: index,
-									text: item.display.as_str(),
-								})
-						})
-				);
-
-				filtered_items.sort();
-			}
+			filtered_items = refilter_and_clamp(
+				&self.items, &filter_string, &matcher,
+				&mut caret_index, &mut selected_index, &mut offset, max_visible_items,
+			);
 
-			// Keep indices in bounds
-			caret_index = caret_index.min(filter_string.len());
+			self.maybe_load_more(filtered_items.len(), offset, max_visible_items, &mut load_handle);
 
-			if !filtered_items.is_empty() {
-				selected_index = selected_index.min(filtered_items.len() - 1);
-			}
+			let selected_original_index = filtered_items.get(selected_index).map(|item| item.original_index);
 
-			// Make sure max number of items possible are visible.
-			offset = offset.min(filtered_items.len().saturating_sub(max_visible_items));
+			// Cancel and respawn the background preview fetch when the selection changes.
+			if self.preview.is_some() && preview_index != selected_original_index {
+				if let Some(handle) = preview_handle.take() {
+					handle.abort();
+				}
 
-			// Make sure selection is in view
-			if selected_index >= offset + max_visible_items {
-				offset = selected_index - max_visible_items + 1;
-			} else if selected_index < offset {
-				offset = selected_index;
+				preview_lines = None;
+				preview_index = selected_original_index;
+				preview_handle = selected_original_index.map(|index| {
+					let preview_fn = self.preview.as_ref().unwrap();
+					tokio::spawn(preview_fn(&self.items[index].value))
+				});
 			}
 
 			viewport.draw(|mut ctx| {
+				// Drop the preview pane entirely if there isn't room for both columns.
+				let show_preview = self.preview.is_some() && ctx.usable_width >= MIN_WIDTH_FOR_PREVIEW;
+
+				let list_width = match show_preview {
+					true => ctx.usable_width / 2,
+					false => ctx.usable_width,
+				};
+
 				ctx.print(&self.prompt_text);
 				ctx.print(&filter_string);
 
+				if load_handle.is_some() {
+					ctx.print(" (loading more...)");
+				}
+
 				// Render list.
-				for (row, (filter_index, &FilteredItem{ text, .. })) in filtered_items.iter().enumerate().skip(offset).take(max_visible_items).enumerate() {
+				for (row, (filter_index, &FilteredItem{ text, original_index, .. })) in filtered_items.iter().enumerate().skip(offset).take(max_visible_items).enumerate() {
 					let is_selected = filter_index == selected_index;
-					let marker = match is_selected {
-						true => '>',
-						false => ' ',
-					};
 
 					if is_selected {
 						ctx.set_fg_color(Color::Black);
@@ -142,88 +370,264 @@ impl<T> FilterableList<T> {
 						ctx.print_at("> ", row as u16 + 1, 0);
 					}
 
-					ctx.print_at(text, row as u16 + 1, 2);
+					match self.row_renderer.as_ref() {
+						Some(renderer) => renderer(&self.items[original_index].value, &mut ctx, row as u16 + 1, 2, list_width),
+						None => {
+							let text = truncate_to_width(text, list_width.saturating_sub(2) as usize);
+							ctx.print_at(&text, row as u16 + 1, 2);
+						}
+					}
 
 					ctx.reset_color();
 				}
 
+				// Render preview, clipped to the right column's width and the viewport's height.
+				// Lines come pre-highlighted, so we clip colored runs rather than word-wrapping.
+				if show_preview {
+					let preview_column = list_width + 1;
+					let preview_width = ctx.usable_width - preview_column;
+
+					match preview_lines.as_ref() {
+						Some(lines) => {
+							for (row, line) in lines.iter().take(ctx.usable_height as usize).enumerate() {
+								ctx.move_to(row as u16, preview_column);
+								ctx.print_styled(&clip_to_width(line, preview_width as usize));
+							}
+						}
+
+						None if preview_handle.is_some() => ctx.print_at("Loading...", 0, preview_column),
+						None => {}
+					}
+				}
+
 				// Move visual cursor to caret position
 				ctx.move_to(0, self.prompt_text.len() as u16 + caret_index as u16);
 			});
 
-			let _guard = start_raw_mode()?;
+			tokio::select! {
+				event = events.next() => match event {
+					Some(Ok(Event::Key(KeyEvent{ code, modifiers, kind: KeyEventKind::Press, .. }))) => {
+						match handle_navigation_key(code, modifiers, filtered_items.len(), max_visible_items, &mut filter_string, &mut caret_index, &mut selected_index) {
+							NavOutcome::Confirm => break 'main,
+							NavOutcome::Cancel => anyhow::bail!("Cancelled"),
+							NavOutcome::Handled | NavOutcome::Unhandled => {}
+						}
+					}
 
-			'events: loop {
-				match event::read()? {
-					Event::Key(KeyEvent{ code, modifiers, kind: KeyEventKind::Press, .. }) => {
-						match (code, modifiers) {
-							(KeyCode::Enter, _) if !filtered_items.is_empty() => break 'main,
+					Some(Ok(Event::Resize(width, height))) => {
+						viewport.terminal_width = width;
+						viewport.terminal_height = height;
+					}
 
-							(KeyCode::Char('c'), KeyModifiers::CONTROL) | (KeyCode::Esc, _) => {
-								anyhow::bail!("Cancelled")
-							}
+					Some(Ok(_)) => {}
+					Some(Err(err)) => return Err(err.into()),
+					None => anyhow::bail!("Terminal event stream ended unexpectedly"),
+				},
 
-							// Note: ctrl+backspace produces ^h on my machine.
-							(KeyCode::Backspace, KeyModifiers::CONTROL) | (KeyCode::Char('h'), KeyModifiers::CONTROL) => {
-								// Not quite right but whatever
-								filter_string.clear();
-								caret_index = 0;
-							}
+				result = async { preview_handle.as_mut().unwrap().await }, if preview_handle.is_some() => {
+					preview_handle = None;
 
-							(KeyCode::Backspace, _) => if let Some(index) = caret_index.checked_sub(1) {
-								filter_string.remove(index);
-								caret_index -= 1;
-							}
+					if let Ok(text) = result {
+						preview_lines = Some(crate::highlight::highlight_text(&text));
+					}
+				}
 
-							(KeyCode::Delete, _) => if !filter_string.is_empty() {
-								filter_string.remove(caret_index);
-							}
+				result = async { load_handle.as_mut().unwrap().await }, if load_handle.is_some() => {
+					load_handle = None;
+
+					match result {
+						Ok(Ok(batch)) => self.apply_loaded_batch(batch),
+						Ok(Err(err)) => return Err(err),
+						Err(err) => return Err(err.into()),
+					}
+				}
+			}
+		}
+
+		anyhow::ensure!(selected_index < filtered_items.len());
+
+		let item_index = filtered_items[selected_index].original_index;
+		Ok(self.items.remove(item_index).value)
+	}
+
+	/// Like [`run`](Self::run), but lets the user tick any number of items with
+	/// Space/Tab before confirming with Enter. Ctrl+A toggles all currently
+	/// filtered items.
+	pub async fn run_multi(mut self) -> anyhow::Result<Vec<T>> {
+		anyhow::ensure!(!self.items.is_empty());
+
+		let mut viewport = InlineViewport::start(self.items.len() + 1)?;
+
+		let mut selected_index = 0usize;
+		let mut caret_index = 0usize;
+		let mut offset = 0;
+		let mut filter_string = String::new();
+		let mut checked: HashSet<usize> = HashSet::new();
+
+		// The in-flight batch fetch kicked off by `maybe_load_more`, if any.
+		let mut load_handle: Option<JoinHandle<anyhow::Result<Vec<(String, T)>>>> = None;
+
+		let matcher = SkimMatcherV2::default();
+
+		let mut filtered_items: Vec<FilteredItem> = Vec::new();
+
+		let _raw_mode_guard = start_raw_mode()?;
+		let mut events = EventStream::new();
+
+		'main: loop {
+			let max_visible_items = viewport.usable_height() as usize - 1;
+
+			filtered_items = refilter_and_clamp(
+				&self.items, &filter_string, &matcher,
+				&mut caret_index, &mut selected_index, &mut offset, max_visible_items,
+			);
+
+			self.maybe_load_more(filtered_items.len(), offset, max_visible_items, &mut load_handle);
+
+			viewport.draw(|mut ctx| {
+				ctx.print(&self.prompt_text);
+				ctx.print(&filter_string);
+
+				if load_handle.is_some() {
+					ctx.print(" (loading more...)");
+				}
+
+				// Render list.
+				for (row, (filter_index, &FilteredItem{ text, original_index, .. })) in filtered_items.iter().enumerate().skip(offset).take(max_visible_items).enumerate() {
+					let is_selected = filter_index == selected_index;
+					let is_checked = checked.contains(&original_index);
+
+					if is_selected {
+						ctx.set_fg_color(Color::Black);
+						ctx.set_bg_color(Color::White);
 
-							(KeyCode::Home, _) => { caret_index = 0; }
-							(KeyCode::End, _) => { caret_index = filter_string.len(); }
+						ctx.print_at("> ", row as u16 + 1, 0);
+					}
 
-							(KeyCode::Left, _) => { caret_index = caret_index.saturating_sub(1); }
-							(KeyCode::Right, _) => { caret_index += 1; }
+					let marker = match is_checked {
+						true => "[x] ",
+						false => "[ ] ",
+					};
 
-							(KeyCode::Up, _) => { selected_index = selected_index.saturating_sub(1); }
-							(KeyCode::Down, _) => { selected_index += 1; }
+					ctx.print_at(marker, row as u16 + 1, 2);
+					ctx.print_at(text, row as u16 + 1, 2 + marker.len() as u16);
 
-							(KeyCode::PageUp, KeyModifiers::CONTROL) => { selected_index = 0; }
-							(KeyCode::PageDown, KeyModifiers::CONTROL) => { selected_index = filtered_items.len(); }
+					ctx.reset_color();
+				}
+
+				// Move visual cursor to caret position
+				ctx.move_to(0, self.prompt_text.len() as u16 + caret_index as u16);
+			});
 
-							(KeyCode::PageUp, _) => { selected_index = selected_index.saturating_sub(max_visible_items); }
-							(KeyCode::PageDown, _) => { selected_index += max_visible_items; }
+			tokio::select! {
+				event = events.next() => match event {
+					Some(Ok(Event::Key(KeyEvent{ code, modifiers, kind: KeyEventKind::Press, .. }))) => {
+						match (code, modifiers) {
+							(KeyCode::Char('a'), KeyModifiers::CONTROL) => {
+								let all_checked = filtered_items.iter().all(|item| checked.contains(&item.original_index));
+								for item in filtered_items.iter() {
+									if all_checked {
+										checked.remove(&item.original_index);
+									} else {
+										checked.insert(item.original_index);
+									}
+								}
+							}
 
-							(KeyCode::Char(ch), _) => if ch.is_ascii() {
-								filter_string.insert(caret_index, ch);
-								caret_index += 1;
+							(KeyCode::Char(' '), _) | (KeyCode::Tab, _) => if let Some(item) = filtered_items.get(selected_index) {
+								if !checked.insert(item.original_index) {
+									checked.remove(&item.original_index);
+								}
 							}
 
-							_ => {}
+							_ => match handle_navigation_key(code, modifiers, filtered_items.len(), max_visible_items, &mut filter_string, &mut caret_index, &mut selected_index) {
+								NavOutcome::Confirm => break 'main,
+								NavOutcome::Cancel => anyhow::bail!("Cancelled"),
+								NavOutcome::Handled | NavOutcome::Unhandled => {}
+							}
 						}
-
-						break 'events
 					}
 
-					Event::Resize(width, height) => {
+					Some(Ok(Event::Resize(width, height))) => {
 						viewport.terminal_width = width;
 						viewport.terminal_height = height;
-						break 'events
 					}
 
-					_ => {}
+					Some(Ok(_)) => {}
+					Some(Err(err)) => return Err(err.into()),
+					None => anyhow::bail!("Terminal event stream ended unexpectedly"),
+				},
+
+				result = async { load_handle.as_mut().unwrap().await }, if load_handle.is_some() => {
+					load_handle = None;
+
+					match result {
+						Ok(Ok(batch)) => self.apply_loaded_batch(batch),
+						Ok(Err(err)) => return Err(err),
+						Err(err) => return Err(err.into()),
+					}
 				}
 			}
 		}
 
-		anyhow::ensure!(selected_index < filtered_items.len());
+		// Enter with nothing ticked confirms just the highlighted row, matching fzf.
+		if checked.is_empty() {
+			if let Some(item) = filtered_items.get(selected_index) {
+				checked.insert(item.original_index);
+			}
+		}
 
-		let item_index = filtered_items[selected_index].original_index;
-		Ok(self.items.remove(item_index).value)
+		let mut indices: Vec<_> = checked.into_iter().collect();
+		indices.sort_unstable();
+
+		// Remove highest index first so earlier indices stay valid, then restore
+		// original order.
+		let mut result: Vec<_> = indices.iter().rev()
+			.map(|&index| self.items.remove(index).value)
+			.collect();
+
+		result.reverse();
+		Ok(result)
 	}
 }
 
 
+/// Clip `text` to at most `width` columns, assuming ASCII content.
+pub(crate) fn truncate_to_width(text: &str, width: usize) -> String {
+	if text.len() <= width {
+		return text.to_owned();
+	}
+
+	text.chars().take(width).collect()
+}
+
+/// Clip a pre-highlighted preview line to at most `width` columns, dropping or
+/// truncating runs once the budget is used up. Borrows back out of `line` so the
+/// caller can hand the result straight to [`ViewportDrawContext::print_styled`].
+fn clip_to_width(line: &[(Color, String)], width: usize) -> Vec<(Color, &str)> {
+	let mut remaining = width;
+	let mut clipped = Vec::new();
+
+	for (color, text) in line {
+		if remaining == 0 {
+			break;
+		}
+
+		let len = text.chars().count();
+		if len <= remaining {
+			clipped.push((*color, text.as_str()));
+			remaining -= len;
+		} else {
+			let end = text.char_indices().nth(remaining).map(|(i, _)| i).unwrap_or(text.len());
+			clipped.push((*color, &text[..end]));
+			remaining = 0;
+		}
+	}
+
+	clipped
+}
+
+
 
 
 
@@ -257,6 +661,18 @@ impl ViewportDrawContext {
 		self.out.queue(style::Print(s.as_ref())).unwrap();
 	}
 
+	/// Print a line built from colored runs, e.g. syntax-highlighted preview
+	/// text from [`highlight::highlight_text`](crate::highlight::highlight_text).
+	/// Prints at the current cursor position, advancing as it goes.
+	pub fn print_styled(&mut self, segments: &[(Color, &str)]) {
+		for &(color, text) in segments {
+			self.set_fg_color(color);
+			self.print(text);
+		}
+
+		self.reset_color();
+	}
+
 	pub fn move_to(&mut self, row: u16, column: u16) {
 		self.out.queue(cursor::MoveTo(column, self.start_row + row)).unwrap();
 	}